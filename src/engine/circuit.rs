@@ -1,6 +1,9 @@
 // Circuit Analyzer
 use std::collections::HashMap;
 use bitvec::prelude::*;
+use num_complex::Complex64;
+
+use crate::engine::kernels;
 
 /// Represents a simple Quantum Gate for analysis
 #[derive(Debug, Clone)]
@@ -8,6 +11,11 @@ pub struct GateOp {
     pub name: String,
     pub targets: Vec<usize>,
     pub params: Vec<f64>,
+    /// Pre-computed 2x2 matrix for a fused run of single-qubit gates.
+    /// `Some` means the fusion pass already collapsed one-or-more gates into
+    /// this block and the execution loop can feed it straight to the kernel;
+    /// `None` means the matrix is resolved from `name`/`params` at run time.
+    pub fused_matrix: Option<[Complex64; 4]>,
 }
 
 /// The "Global Access Schedule"
@@ -32,6 +40,54 @@ impl CircuitAnalyzer {
         }
     }
 
+    /// Gate-fusion pass (in the spirit of Qiskit's Optimize1qGatesDecomposition).
+    ///
+    /// Scans the op list and collapses every maximal run of single-qubit gates
+    /// acting on the *same* target qubit into a single block carrying the
+    /// pre-multiplied 2x2 matrix, so three H/RZ/H in a row cost one on-disk
+    /// sweep instead of three. A multi-qubit gate touching qubit `q` (as target
+    /// or control) is a fusion barrier for `q`: it closes the open run so later
+    /// gates on `q` start a fresh block.
+    ///
+    /// Because gate `U_n` is applied last, the fused matrix is the right-to-left
+    /// product `U_n . ... . U_2 . U_1`. Single-qubit gates on disjoint qubits
+    /// commute, so folding a gate into an earlier same-qubit block never crosses
+    /// a gate it fails to commute with.
+    pub fn fuse_gates(&self, gates: &[GateOp]) -> Vec<GateOp> {
+        let mut out: Vec<GateOp> = Vec::with_capacity(gates.len());
+        // qubit -> index in `out` of the currently open fusible run on it
+        let mut open: HashMap<usize, usize> = HashMap::new();
+
+        for gate in gates {
+            if gate.targets.len() == 1 {
+                let q = gate.targets[0];
+                let m = kernels::get_matrix(&gate.name, &gate.params);
+                if let Some(&oi) = open.get(&q) {
+                    // Fold into the open block: new gate is applied last.
+                    let prev = out[oi].fused_matrix.expect("open run carries a matrix");
+                    out[oi].fused_matrix = Some(kernels::mat2_mul(m, prev));
+                } else {
+                    let mut fused = gate.clone();
+                    fused.fused_matrix = Some(m);
+                    open.insert(q, out.len());
+                    out.push(fused);
+                }
+            } else {
+                // Multi-qubit gate: barrier for every wire it touches.
+                for &q in &gate.targets {
+                    open.remove(&q);
+                }
+                out.push(gate.clone());
+            }
+        }
+
+        let collapsed = gates.len().saturating_sub(out.len());
+        if collapsed > 0 {
+            println!("[Analyzer] Gate fusion collapsed {} single-qubit gates.", collapsed);
+        }
+        out
+    }
+
     /// The "Lookahead" logic
     pub fn analyze(&self, gates: &[GateOp]) -> AccessSchedule {
         let mut timeline = HashMap::new();
@@ -94,3 +150,93 @@ impl CircuitAnalyzer {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::kernels;
+    use num_complex::Complex64;
+
+    /// Freshly initialized |0...0> state vector (8-byte aligned for the kernel's
+    /// Complex64 reinterpret).
+    fn zero_state(num_qubits: usize) -> Vec<Complex64> {
+        let mut sv = vec![Complex64::new(0.0, 0.0); 1usize << num_qubits];
+        sv[0] = Complex64::new(1.0, 0.0);
+        sv
+    }
+
+    fn as_bytes(sv: &mut [Complex64]) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(sv.as_mut_ptr() as *mut u8, sv.len() * 16) }
+    }
+
+    fn op(name: &str, target: usize, params: Vec<f64>) -> GateOp {
+        GateOp {
+            name: name.to_string(),
+            targets: vec![target],
+            params,
+            fused_matrix: None,
+        }
+    }
+
+    #[test]
+    fn fused_matches_unfused_amplitudes() {
+        // A maximal run of single-qubit gates on one wire.
+        let gates = vec![
+            op("H", 0, vec![]),
+            op("RZ", 0, vec![0.7]),
+            op("H", 0, vec![]),
+        ];
+
+        // Unfused: apply each gate's matrix in turn.
+        let mut unfused = zero_state(1);
+        for g in &gates {
+            kernels::apply_single_qubit_gate(
+                as_bytes(&mut unfused),
+                1,
+                g.targets[0],
+                kernels::get_matrix(&g.name, &g.params),
+            );
+        }
+
+        // Fused: one sweep with the pre-multiplied block.
+        let analyzer = CircuitAnalyzer::new(1);
+        let fused_ops = analyzer.fuse_gates(&gates);
+        assert_eq!(fused_ops.len(), 1, "run should collapse to a single block");
+        let mut fused = zero_state(1);
+        kernels::apply_single_qubit_gate(
+            as_bytes(&mut fused),
+            1,
+            0,
+            fused_ops[0].fused_matrix.expect("fused block carries a matrix"),
+        );
+
+        for idx in 0..2 {
+            assert!(
+                (unfused[idx] - fused[idx]).norm() < 1e-12,
+                "amplitude {} diverged between fused and unfused",
+                idx
+            );
+        }
+    }
+
+    #[test]
+    fn two_qubit_gate_is_a_fusion_barrier() {
+        // CX between two single-qubit runs on qubit 0 must not fuse across it.
+        let gates = vec![
+            op("H", 0, vec![]),
+            GateOp {
+                name: "CX".to_string(),
+                targets: vec![0, 1],
+                params: vec![],
+                fused_matrix: None,
+            },
+            op("H", 0, vec![]),
+        ];
+        let analyzer = CircuitAnalyzer::new(2);
+        let fused_ops = analyzer.fuse_gates(&gates);
+        assert_eq!(fused_ops.len(), 3, "barrier must prevent fusion across it");
+        assert!(fused_ops[0].fused_matrix.is_some());
+        assert!(fused_ops[1].fused_matrix.is_none());
+        assert!(fused_ops[2].fused_matrix.is_some());
+    }
+}
+
@@ -2,9 +2,12 @@
 use pyo3::prelude::*;
 
 use std::collections::hash_map::DefaultHasher;
-use std::fs;
+use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 
+/// Page size in bytes (matches the memory manager's own constant).
+const PAGE_SIZE: usize = 4096;
+
 use crate::engine::circuit::{AccessSchedule, CircuitAnalyzer, GateOp};
 use crate::engine::io::AsyncIoEngine;
 use crate::engine::kernels;
@@ -21,6 +24,17 @@ pub struct SimulatorController {
     // [Phase 3] Caching Mechanism for VQA scenarios
     cached_schedule: Option<AccessSchedule>,
     last_circuit_hash: u64,
+    // [Phase 3] Resident-set budget in pages. `None` means unbounded (mmap
+    // everything and let the OS page); `Some(n)` caps DRAM to `n` pages and
+    // evicts with a Belady furthest-future-use policy after each gate.
+    dram_budget_pages: Option<usize>,
+    // [Phase 3] Cold-page compression config: (spill_path, in-DRAM budget bytes).
+    // `None` disables compression; pages page in/out of the raw backing store.
+    compression: Option<(String, usize)>,
+    // [Phase 3] Content hash of the base snapshot captured at initialize time;
+    // stamped into every journal segment so a restore onto an advanced or
+    // mismatched base is rejected.
+    base_snapshot_hash: u64,
 }
 
 #[pymethods]
@@ -34,14 +48,83 @@ impl SimulatorController {
             lookahead_depth: 1,
             cached_schedule: None,
             last_circuit_hash: 0,
+            dram_budget_pages: None,
+            compression: None,
+            base_snapshot_hash: 0,
+        }
+    }
+
+    /// Enable transparent compression of cold pages. Up to `mem_budget` bytes of
+    /// compressed blobs are kept in DRAM; the rest spill to `spill_path`. Takes
+    /// effect immediately if memory is already initialized, otherwise at
+    /// `initialize`.
+    pub fn enable_compression(&mut self, spill_path: String, mem_budget: usize) {
+        self.compression = Some((spill_path.clone(), mem_budget));
+        if let Some(mem) = self.memory.as_mut() {
+            mem.enable_compression(spill_path, mem_budget);
         }
+        println!("[Rust Core] Cold-page compression enabled");
+    }
+
+    /// Achieved cold-page compression ratio (uncompressed / compressed), or 1.0
+    /// when compression is disabled or nothing has been compressed yet.
+    pub fn compression_ratio(&self) -> f64 {
+        self.memory.as_ref().map_or(1.0, QuantumMemoryManager::compression_ratio)
+    }
+
+    /// Cap resident DRAM to `bytes`, rounded down to whole pages. Once set, the
+    /// execution loop evicts pages after every gate so resident memory never
+    /// exceeds the budget — turning "mmap everything and hope" into true
+    /// software-managed paging.
+    pub fn set_dram_budget(&mut self, bytes: usize) {
+        let pages = bytes / PAGE_SIZE;
+        self.dram_budget_pages = Some(pages);
+        println!("[Rust Core] DRAM budget set to {} pages ({} bytes)", pages, bytes);
+    }
+
+    /// Decompose a gate (or a fused single-qubit block expressed as a gate name
+    /// + params) into canonical U3 Euler angles `(global_phase, theta, phi,
+    /// lambda)`, so circuits can be round-tripped back to Python as U3s.
+    pub fn canonical_u3(&self, name: String, params: Vec<f64>) -> (f64, f64, f64, f64) {
+        let matrix = kernels::get_matrix(&name, &params);
+        kernels::zyz_decompose(matrix)
+    }
+
+    /// Decompose an arbitrary 2x2 unitary -- e.g. the `fused_matrix` produced by
+    /// the fusion pass -- into canonical U3 Euler angles. `entries` is the
+    /// row-major matrix flattened to eight reals `[re00, im00, re01, im01, re10,
+    /// im10, re11, im11]`, the shape a fused block serializes to over the FFI
+    /// boundary. This is the path that lets a collapsed block be reported back to
+    /// Python as a single U3.
+    pub fn canonical_u3_from_matrix(&self, entries: Vec<f64>) -> PyResult<(f64, f64, f64, f64)> {
+        if entries.len() != 8 {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "expected 8 reals for a 2x2 complex matrix, got {}",
+                entries.len()
+            )));
+        }
+        let matrix = [
+            num_complex::Complex64::new(entries[0], entries[1]),
+            num_complex::Complex64::new(entries[2], entries[3]),
+            num_complex::Complex64::new(entries[4], entries[5]),
+            num_complex::Complex64::new(entries[6], entries[7]),
+        ];
+        Ok(kernels::zyz_decompose(matrix))
     }
 
     /// Phase 1: Initialize Memory
     pub fn initialize(&mut self) -> PyResult<()> {
-        let mem = QuantumMemoryManager::new(self.num_qubits, &self.backing_store)
+        let mut mem = QuantumMemoryManager::new(self.num_qubits, &self.backing_store)
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyIOError, _>(e.to_string()))?;
 
+        if let Some((spill_path, mem_budget)) = self.compression.clone() {
+            mem.enable_compression(spill_path, mem_budget);
+        }
+
+        // Fingerprint the freshly-initialized base state; journals are deltas
+        // relative to this snapshot.
+        self.base_snapshot_hash = mem.content_hash();
+
         self.memory = Some(mem);
         println!(
             "[Rust Core] Initialized {} Qubits on SSD: {}",
@@ -50,25 +133,50 @@ impl SimulatorController {
         Ok(())
     }
 
-    /// Create a checkpoint snapshot of the current state
-    pub fn create_checkpoint(&self, checkpoint_path: String) -> PyResult<()> {
-        if let Some(mem) = &self.memory {
-            // Flush memory to disk
-            mem.snapshot()
-                .map_err(|e| PyErr::new::<pyo3::exceptions::PyIOError, _>(e.to_string()))?;
+    /// Create an incremental checkpoint by appending the pages dirtied since the
+    /// last checkpoint to a write-ahead journal, instead of copying the whole
+    /// multi-gigabyte backing file. Cost is O(pages touched).
+    pub fn create_checkpoint(&mut self, checkpoint_path: String) -> PyResult<()> {
+        let base_hash = self.base_snapshot_hash;
+        let mem = self.memory.as_mut().ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>("Memory not initialized")
+        })?;
 
-            // Copy backing file to checkpoint location
-            // Note: Copies entire file. Use COW (reflink) in production for efficiency.
-            fs::copy(&self.backing_store, &checkpoint_path)
-                .map_err(|e| PyErr::new::<pyo3::exceptions::PyIOError, _>(e.to_string()))?;
+        let written = mem
+            .append_journal(&checkpoint_path, base_hash)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyIOError, _>(e.to_string()))?;
 
-            println!("[Rust Core] Checkpoint created at {}", checkpoint_path);
-            Ok(())
-        } else {
-            Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
-                "Memory not initialized",
-            ))
-        }
+        println!(
+            "[Rust Core] Checkpoint journaled {} dirty page(s) to {}",
+            written, checkpoint_path
+        );
+        Ok(())
+    }
+
+    /// Restore state by replaying a write-ahead journal's deltas onto the base
+    /// snapshot. This is delta-replay-onto-base: the caller must have the base
+    /// loaded (e.g. a fresh `initialize`) before calling — the mmap's current
+    /// content hash must match the base the journal was recorded against, or
+    /// the restore is rejected. It does not itself reproduce a base that has
+    /// since been advanced by additional gates.
+    pub fn restore_checkpoint(&mut self, checkpoint_path: String) -> PyResult<()> {
+        let mem = self.memory.as_mut().ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>("Memory not initialized")
+        })?;
+
+        // The journal's deltas are valid only on top of the base they were
+        // captured against; hashing the actual current contents lets us reject
+        // a replay onto an advanced/mutated base instead of silently corrupting.
+        let current_base = mem.content_hash();
+        let applied = mem
+            .restore_journal(&checkpoint_path, current_base)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyIOError, _>(e.to_string()))?;
+
+        println!(
+            "[Rust Core] Restored {} page delta(s) from {}",
+            applied, checkpoint_path
+        );
+        Ok(())
     }
 
     /// Phase 2: Execute Circuit (The Main Loop)
@@ -88,6 +196,26 @@ impl SimulatorController {
         let mut hasher = DefaultHasher::new();
 
         for i in 0..gate_names.len() {
+            // Reject unknown gates and arity mismatches instead of silently
+            // discarding parameters / applying identity.
+            match kernels::expected_param_count(&gate_names[i]) {
+                Some(arity) if params[i].len() != arity => {
+                    return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                        "Gate '{}' expects {} parameter(s), got {}",
+                        gate_names[i],
+                        arity,
+                        params[i].len()
+                    )));
+                }
+                Some(_) => {}
+                None => {
+                    return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                        "Unknown gate '{}'",
+                        gate_names[i]
+                    )));
+                }
+            }
+
             // Hash structure (name + targets), ignore parameters
             gate_names[i].hash(&mut hasher);
             targets[i].hash(&mut hasher);
@@ -96,17 +224,22 @@ impl SimulatorController {
                 name: gate_names[i].clone(),
                 targets: targets[i].clone(),
                 params: params[i].clone(),
+                fused_matrix: None,
             });
         }
         let current_hash = hasher.finish();
 
+        // Collapse consecutive single-qubit gates before scheduling so each
+        // fused block is one on-disk sweep instead of one sweep per gate.
+        let analyzer = CircuitAnalyzer::new(self.num_qubits);
+        let ops = analyzer.fuse_gates(&ops);
+
         // Analyze circuit or reuse cached schedule (VQA optimization)
         let schedule = if self.last_circuit_hash == current_hash && self.cached_schedule.is_some() {
             println!("[Rust Core] Cache HIT. Reusing Analysis Schedule.");
             self.cached_schedule.as_ref().unwrap().clone()
         } else {
             println!("[Rust Core] Cache MISS. Running Circuit Analysis...");
-            let analyzer = CircuitAnalyzer::new(self.num_qubits);
             let schedule = analyzer.analyze(&ops);
 
             self.cached_schedule = Some(schedule.clone());
@@ -121,6 +254,10 @@ impl SimulatorController {
 
         println!("[Rust Core] Starting execution loop with Deterministic Prefetching...");
 
+        let budget = self.dram_budget_pages;
+        let compression_on = self.compression.is_some();
+        let num_ops = ops.len();
+
         // 4. Execution Loop
         for i in 0..ops.len() {
             let op = &ops[i];
@@ -135,6 +272,18 @@ impl SimulatorController {
                         .submit_prefetch(future_pages, mem.as_ptr())
                         .unwrap_or(0);
 
+                    // Restore any compressed cold pages back into the mmap
+                    // before the gate reads them, instead of faulting from the
+                    // raw backing store.
+                    if compression_on {
+                        mem.prefetch_decompress(future_pages);
+                    }
+
+                    // Prefetched pages are now (being) brought into DRAM.
+                    if budget.is_some() {
+                        mem.mark_resident(future_pages);
+                    }
+
                     // Debug output for first few gates
                     if i < 3 {
                         println!(
@@ -148,19 +297,89 @@ impl SimulatorController {
             // Cleanup completed IO tasks (don't let CQ overflow)
             io_engine.reap_completions();
 
+            // A gate with no targets (e.g. a global phase / barrier) touches no
+            // amplitudes — skip it rather than indexing op.targets[0].
+            if op.targets.is_empty() {
+                continue;
+            }
+
             // Safety check: OS handles page faults automatically via mmap
             // If prefetch is slow, mmap will block on access until pages are loaded
 
             // Apply gate operation
             // Pages should be prefetched from iteration (i - lookahead_depth)
-            let matrix = kernels::get_matrix(&op.name, &op.params);
-
-            kernels::apply_single_qubit_gate(
-                mem.as_mut_slice(),
-                self.num_qubits,
-                op.targets[0],
-                matrix,
-            );
+            // A fused block carries its pre-multiplied matrix directly;
+            // otherwise resolve it from the gate name/params.
+            let matrix = match op.fused_matrix {
+                Some(m) => m,
+                None => kernels::get_matrix(&op.name, &op.params),
+            };
+
+            if op.targets.len() >= 2 {
+                // Controlled gate: targets[0] is the control, targets[1] the
+                // target. `matrix` is the op applied on the control=1 subspace.
+                kernels::apply_controlled_gate(
+                    mem.as_mut_slice(),
+                    op.targets[0],
+                    op.targets[1],
+                    matrix,
+                );
+            } else {
+                kernels::apply_single_qubit_gate(
+                    mem.as_mut_slice(),
+                    self.num_qubits,
+                    op.targets[0],
+                    matrix,
+                );
+            }
+
+            // Pages this gate wrote: a single-qubit gate writes every page; a
+            // controlled gate writes the pages holding control=1 amplitudes.
+            // (The analyzer's schedule covers only the bit-0 half of a stride
+            // wider than a page, so it is not used as the write set.)
+            let amps_per_page = PAGE_SIZE / 16;
+            let c = op.targets[0];
+            let mut written = mem.dirty_bitmap.clone();
+            for pg in 0..written.len() {
+                let hit = op.targets.len() == 1
+                    || (1usize << c) < amps_per_page
+                    || ((pg * amps_per_page) >> c) & 1 == 1;
+                written.set(pg, hit);
+            }
+            // Record them so the next checkpoint only journals the deltas.
+            mem.mark_dirty(&written);
+
+            // --- B. EVICTION STEP (Phase 3) ---
+            // The gate just faulted its own pages in; account for them and, if
+            // the resident set now exceeds the DRAM budget, evict the pages
+            // whose next use is furthest in the future (Belady). A low-qubit
+            // gate legitimately touches every page, so the resident set really
+            // can fill up — the budget then forces genuine eviction.
+            if let Some(budget_pages) = budget {
+                mem.mark_resident(&written);
+
+                let resident_count = mem.resident_page_count();
+                if resident_count > budget_pages {
+                    // Soonest future gate that needs each *resident* page. Stop
+                    // scanning once every resident page has a known next use, so
+                    // this is not a full rescan of the remaining circuit on
+                    // every gate.
+                    let mut next_use: HashMap<usize, usize> = HashMap::new();
+                    for future in (i + 1)..num_ops {
+                        if next_use.len() == resident_count {
+                            break;
+                        }
+                        if let Some(pages) = schedule.timeline.get(&future) {
+                            for pg in pages.iter_ones() {
+                                if mem.resident_bitmap[pg] {
+                                    next_use.entry(pg).or_insert(future);
+                                }
+                            }
+                        }
+                    }
+                    mem.enforce_budget(budget_pages, &next_use);
+                }
+            }
         }
 
         println!("[Rust Core] Execution finished.");
@@ -47,71 +47,270 @@ pub fn apply_single_qubit_gate(
     });
 }
 
-/// Apply Controlled-NOT (CX) gate
-/// Logic: If Control Qubit is |1>, apply X on Target Qubit.
-/// This swaps amplitudes of |...1,0...> and |...1,1...> states.
-pub fn apply_cnot(mmap_slice: &mut [u8], _num_qubits: usize, control: usize, target: usize) {
-    // Cast raw bytes to Complex64
+/// Apply an arbitrary controlled single-qubit gate.
+///
+/// For every basis index whose `control` bit is 1, the 2x2 `matrix` is applied
+/// to the `target` amplitudes — pairing index `i` (target bit 0) with
+/// `i | (1 << target)` (target bit 1). This is fully general in the relative
+/// ordering of `control` and `target`: we work in the global index space
+/// instead of splitting blocks, so the old "target is higher bit" bail-out is
+/// gone. CNOT is the `X` special case (see [`apply_cnot`]); passing CZ /
+/// controlled-phase / controlled-RY matrices works for free.
+pub fn apply_controlled_gate(
+    mmap_slice: &mut [u8],
+    control: usize,
+    target: usize,
+    matrix: [Complex64; 4],
+) {
     let total_elements = mmap_slice.len() / 16;
     let state_vector = unsafe {
         std::slice::from_raw_parts_mut(mmap_slice.as_mut_ptr() as *mut Complex64, total_elements)
     };
 
-    // Determine strides for control and target qubits
-    let stride_control = 1 << control;
-    let stride_target = 1 << target;
-
-    // CNOT logic: swap amplitudes where control=1
-    // Process in blocks where control qubit is fixed
-    let control_block_size = stride_control * 2;
-
-    state_vector
-        .par_chunks_mut(control_block_size)
-        .for_each(|control_block| {
-            // Split into control=0 and control=1 halves
-            let (_, control_one) = control_block.split_at_mut(stride_control);
-
-            // Within control=1 half, swap target qubit |0> and |1> states
-            if stride_target <= stride_control {
-                // Target is lower or equal bit: process by target stride within control=1 region
-                let target_block_size = stride_target * 2;
-                for target_block in control_one.chunks_mut(target_block_size) {
-                    if target_block.len() >= target_block_size {
-                        let (target_zero, target_one) = target_block.split_at_mut(stride_target);
-                        // Swap amplitudes: |control=1, target=0> <-> |control=1, target=1>
-                        for i in 0..stride_target.min(target_zero.len()).min(target_one.len()) {
-                            std::mem::swap(&mut target_zero[i], &mut target_one[i]);
-                        }
-                    }
-                }
-            } else {
-                // Target is higher bit: need to swap across control blocks
-                // This case is more complex and requires global index tracking
-                // For MVP, we handle the common case where target < control
-                // Full implementation would require additional logic here
+    let control_mask = 1usize << control;
+    let target_mask = 1usize << target;
+
+    // Partition over the pair index space: each pair is visited exactly once,
+    // from its target=0 member, and only when the control bit is set. The two
+    // members of a pair differ only in the target bit, so distinct `i` touch
+    // disjoint amplitudes and the parallel writes never alias.
+    let base = state_vector.as_mut_ptr() as usize;
+    (0..total_elements).into_par_iter().for_each(|i| {
+        if i & target_mask == 0 && i & control_mask != 0 {
+            let j = i | target_mask;
+            unsafe {
+                let sv = base as *mut Complex64;
+                let amp0 = *sv.add(i);
+                let amp1 = *sv.add(j);
+                *sv.add(i) = matrix[0] * amp0 + matrix[1] * amp1;
+                *sv.add(j) = matrix[2] * amp0 + matrix[3] * amp1;
             }
-        });
+        }
+    });
+}
+
+/// Apply Controlled-NOT (CX) gate.
+/// Logic: If Control Qubit is |1>, apply X on Target Qubit.
+/// This swaps amplitudes of |...1,0...> and |...1,1...> states, and is just
+/// [`apply_controlled_gate`] specialized to the Pauli-X matrix.
+pub fn apply_cnot(mmap_slice: &mut [u8], _num_qubits: usize, control: usize, target: usize) {
+    let x = [
+        Complex64::new(0.0, 0.0),
+        Complex64::new(1.0, 0.0),
+        Complex64::new(1.0, 0.0),
+        Complex64::new(0.0, 0.0),
+    ];
+    apply_controlled_gate(mmap_slice, control, target, x);
+}
+
+/// Multiply two 2x2 matrices stored row-major as `[m00, m01, m10, m11]`.
+/// Returns `a . b`; fusion uses this to build `U_n . ... . U_1`.
+pub fn mat2_mul(a: [Complex64; 4], b: [Complex64; 4]) -> [Complex64; 4] {
+    [
+        a[0] * b[0] + a[1] * b[2],
+        a[0] * b[1] + a[1] * b[3],
+        a[2] * b[0] + a[3] * b[2],
+        a[2] * b[1] + a[3] * b[3],
+    ]
 }
 
-/// Helper to generate common gate matrices
-pub fn get_matrix(name: &str, _params: &[f64]) -> [Complex64; 4] {
+/// Number of continuous parameters each known gate consumes.
+/// Returns `None` for unknown gate names (which `get_matrix` would treat as
+/// identity), so `run_circuit` can reject unknown gates and arity mismatches
+/// loudly instead of silently discarding parameters.
+pub fn expected_param_count(name: &str) -> Option<usize> {
     match name.to_uppercase().as_str() {
-        "X" => [
-            Complex64::new(0.0, 0.0),
-            Complex64::new(1.0, 0.0),
-            Complex64::new(1.0, 0.0),
-            Complex64::new(0.0, 0.0),
-        ],
+        "RX" | "RY" | "RZ" | "PHASE" | "P" => Some(1),
+        "U3" | "U" => Some(3),
+        "X" | "Y" | "Z" | "H" | "S" | "T" | "I" | "ID" | "CX" | "CNOT" | "CZ" => Some(0),
+        _ => None,
+    }
+}
+
+/// Helper to generate common gate matrices.
+///
+/// Fixed gates ignore `params`; the parametrized rotations and the general
+/// `U3(theta, phi, lambda)` read them positionally. Callers are expected to
+/// have validated arity (see [`expected_param_count`]); missing parameters
+/// default to zero so a stray call degrades to identity rather than panicking.
+pub fn get_matrix(name: &str, params: &[f64]) -> [Complex64; 4] {
+    let zero = Complex64::new(0.0, 0.0);
+    let one = Complex64::new(1.0, 0.0);
+    let i = Complex64::new(0.0, 1.0);
+    let p = |idx: usize| params.get(idx).copied().unwrap_or(0.0);
+
+    match name.to_uppercase().as_str() {
+        "X" => [zero, one, one, zero],
+        "Y" => [zero, -i, i, zero],
+        "Z" => [one, zero, zero, -one],
         "H" => {
             let val = Complex64::new(FRAC_1_SQRT_2, 0.0);
             [val, val, val, -val]
         }
+        // Controlled gates resolve to the single-qubit op applied on the target
+        // within the control=1 subspace (see apply_controlled_gate).
+        "CX" | "CNOT" => [zero, one, one, zero],
+        "CZ" => [one, zero, zero, -one],
+        "S" => [one, zero, zero, i],
+        "T" => [one, zero, zero, Complex64::from_polar(1.0, std::f64::consts::FRAC_PI_4)],
+        "RX" => {
+            let (s, c) = (p(0) / 2.0).sin_cos();
+            let c = Complex64::new(c, 0.0);
+            let ns = Complex64::new(0.0, -s);
+            [c, ns, ns, c]
+        }
+        "RY" => {
+            let (s, c) = (p(0) / 2.0).sin_cos();
+            let (c, s) = (Complex64::new(c, 0.0), Complex64::new(s, 0.0));
+            [c, -s, s, c]
+        }
+        "RZ" => {
+            let half = p(0) / 2.0;
+            [Complex64::from_polar(1.0, -half), zero, zero, Complex64::from_polar(1.0, half)]
+        }
+        "PHASE" | "P" => [one, zero, zero, Complex64::from_polar(1.0, p(0))],
+        "U3" | "U" => {
+            let (theta, phi, lam) = (p(0), p(1), p(2));
+            let (s, c) = (theta / 2.0).sin_cos();
+            [
+                Complex64::new(c, 0.0),
+                -Complex64::from_polar(s, lam),
+                Complex64::from_polar(s, phi),
+                Complex64::from_polar(c, phi + lam),
+            ]
+        }
         // Default to Identity if unknown
-        _ => [
-            Complex64::new(1.0, 0.0),
-            Complex64::new(0.0, 0.0),
-            Complex64::new(0.0, 0.0),
-            Complex64::new(1.0, 0.0),
-        ],
+        _ => [one, zero, zero, one],
+    }
+}
+
+/// ZYZ (Euler-angle) decomposition of an arbitrary 2x2 unitary.
+///
+/// Returns `(global_phase, theta, phi, lambda)` such that
+/// `U = e^{i * global_phase} * U3(theta, phi, lambda)`, matching the canonical
+/// form produced by [`get_matrix`]. This lets a fused single-qubit block be
+/// round-tripped back to Python as one `U3`. The matrix is first normalized to
+/// SU(2) by dividing out `sqrt(det)`, exactly as Qiskit's one-qubit decomposer.
+pub fn zyz_decompose(m: [Complex64; 4]) -> (f64, f64, f64, f64) {
+    let det = m[0] * m[3] - m[1] * m[2];
+    let coeff = det.sqrt().inv(); // det^{-1/2}
+    let su = [m[0] * coeff, m[1] * coeff, m[2] * coeff, m[3] * coeff];
+
+    let theta = 2.0 * su[2].norm().atan2(su[0].norm());
+    let phi_plus_lambda = 2.0 * su[3].arg();
+    let phi_minus_lambda = 2.0 * su[2].arg();
+    let phi = (phi_plus_lambda + phi_minus_lambda) / 2.0;
+    let lambda = (phi_plus_lambda - phi_minus_lambda) / 2.0;
+    // `-coeff.arg()` is the phase relating `m` to its SU(2) part `su = RZ*RY*RZ`.
+    // But `get_matrix`'s U3 carries an extra `e^{i(phi+lambda)/2}` relative to
+    // that SU(2) form, so peel it off to honour the documented
+    // `U = e^{i*global_phase} * U3(theta, phi, lambda)` contract.
+    let global_phase = -coeff.arg() - (phi + lambda) / 2.0;
+
+    (global_phase, theta, phi, lambda)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Freshly initialized |0...0> state vector (8-byte aligned).
+    fn zero_state(num_qubits: usize) -> Vec<Complex64> {
+        let mut sv = vec![Complex64::new(0.0, 0.0); 1usize << num_qubits];
+        sv[0] = Complex64::new(1.0, 0.0);
+        sv
+    }
+
+    fn as_bytes(sv: &mut [Complex64]) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(sv.as_mut_ptr() as *mut u8, sv.len() * 16) }
+    }
+
+    /// Prepare a Bell pair by H on `control` followed by CNOT(control, target)
+    /// and assert the result is (|00> + |11>) / sqrt(2).
+    fn assert_bell(control: usize, target: usize) {
+        let mut sv = zero_state(2);
+        apply_single_qubit_gate(as_bytes(&mut sv), 2, control, get_matrix("H", &[]));
+        apply_cnot(as_bytes(&mut sv), 2, control, target);
+
+        let s = FRAC_1_SQRT_2;
+        assert!((sv[0].re - s).abs() < 1e-12 && sv[0].im.abs() < 1e-12);
+        assert!((sv[3].re - s).abs() < 1e-12 && sv[3].im.abs() < 1e-12);
+        assert!(sv[1].norm() < 1e-12, "|01> should be empty");
+        assert!(sv[2].norm() < 1e-12, "|10> should be empty");
+    }
+
+    #[test]
+    fn bell_state_every_ordering() {
+        // Both relative orderings must work, including target above control
+        // (the case the old block-splitting kernel silently dropped).
+        assert_bell(0, 1);
+        assert_bell(1, 0);
+    }
+
+    #[test]
+    fn controlled_gate_generalizes_cnot() {
+        // apply_controlled_gate with the X matrix must equal apply_cnot.
+        let x = get_matrix("CX", &[]);
+        for (control, target) in [(0usize, 1usize), (1, 0)] {
+            let mut a = zero_state(2);
+            let mut b = zero_state(2);
+            apply_single_qubit_gate(as_bytes(&mut a), 2, control, get_matrix("H", &[]));
+            apply_single_qubit_gate(as_bytes(&mut b), 2, control, get_matrix("H", &[]));
+
+            apply_cnot(as_bytes(&mut a), 2, control, target);
+            apply_controlled_gate(as_bytes(&mut b), control, target, x);
+
+            for idx in 0..4 {
+                assert!((a[idx] - b[idx]).norm() < 1e-12);
+            }
+        }
+    }
+
+    /// Rebuild the matrix from the decomposition's own contract:
+    /// `U = e^{i*global_phase} * U3(theta, phi, lambda)`.
+    fn reconstruct(d: (f64, f64, f64, f64)) -> [Complex64; 4] {
+        let (global_phase, theta, phi, lambda) = d;
+        let u3 = get_matrix("U3", &[theta, phi, lambda]);
+        let ph = Complex64::from_polar(1.0, global_phase);
+        [ph * u3[0], ph * u3[1], ph * u3[2], ph * u3[3]]
+    }
+
+    #[test]
+    fn zyz_round_trips_to_input_matrix() {
+        let cases = [
+            get_matrix("H", &[]),
+            get_matrix("RX", &[0.9]),
+            get_matrix("RY", &[-1.3]),
+            get_matrix("RZ", &[0.4]),
+            get_matrix("U3", &[0.7, 1.1, -0.5]),
+        ];
+        for m in cases {
+            let back = reconstruct(zyz_decompose(m));
+            for idx in 0..4 {
+                assert!(
+                    (m[idx] - back[idx]).norm() < 1e-12,
+                    "entry {} did not round-trip: {:?} vs {:?}",
+                    idx,
+                    m[idx],
+                    back[idx]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn controlled_z_flips_phase_of_11() {
+        // CZ leaves the equal superposition amplitudes in place but flips the
+        // sign of |11>.
+        let mut sv = zero_state(2);
+        apply_single_qubit_gate(as_bytes(&mut sv), 2, 0, get_matrix("H", &[]));
+        apply_single_qubit_gate(as_bytes(&mut sv), 2, 1, get_matrix("H", &[]));
+        apply_controlled_gate(as_bytes(&mut sv), 0, 1, get_matrix("CZ", &[]));
+
+        let h = 0.5;
+        assert!((sv[0].re - h).abs() < 1e-12);
+        assert!((sv[1].re - h).abs() < 1e-12);
+        assert!((sv[2].re - h).abs() < 1e-12);
+        assert!((sv[3].re + h).abs() < 1e-12, "|11> amplitude should be negated");
     }
 }
@@ -1,12 +1,18 @@
 // Virtual Memory Manager
 use bitvec::prelude::*;
 use memmap2::{MmapMut, MmapOptions};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
 use std::fs::File;
+use std::hash::{Hash, Hasher};
 use std::path::Path;
 
 /// page size typically 4KB, usually defined by system but hardcoded for MVP
 const PAGE_SIZE: usize = 4096;
 
+/// Magic tag prefixing each write-ahead journal segment ("QPJ1").
+const JOURNAL_MAGIC: u32 = 0x5150_4A31;
+
 /// Manages the huge state vector file and its memory mapping.
 /// Implements "Scope Memory" - resources are tied to this struct's lifetime.
 pub struct QuantumMemoryManager {
@@ -15,6 +21,112 @@ pub struct QuantumMemoryManager {
     pub num_qubits: usize,
     pub total_bytes: usize,
     pub resident_bitmap: BitVec, // Tracks which pages are currently in DRAM
+    pub dirty_bitmap: BitVec,    // Tracks pages written since the last checkpoint
+    compressor: Option<PageCompressor>, // Optional cold-page compression layer
+}
+
+/// Transparent per-page compression layer for cold (evicted) pages.
+///
+/// On eviction a page's 4096 bytes are zstd-compressed and held in an in-memory
+/// side map keyed by page index; a blob that would push that cache past its
+/// byte budget spills to a companion file instead. Near-zero amplitude pages — common
+/// for sparse intermediate states — compress enormously. Prefetch decompresses
+/// a page back into the mmap region before the gate reads it, falling back to
+/// plain mmap paging for any page that was never compressed.
+pub struct PageCompressor {
+    cache: HashMap<usize, Vec<u8>>,       // page_idx -> in-memory compressed blob
+    spill: HashMap<usize, (u64, u32)>,    // page_idx -> (offset, len) in spill file
+    spill_path: String,
+    spill_file: Option<File>,
+    mem_budget: usize,
+    mem_used: usize,
+    total_uncompressed: u64,
+    total_compressed: u64,
+}
+
+impl PageCompressor {
+    fn new(spill_path: String, mem_budget: usize) -> Self {
+        Self {
+            cache: HashMap::new(),
+            spill: HashMap::new(),
+            spill_path,
+            spill_file: None,
+            mem_budget,
+            mem_used: 0,
+            total_uncompressed: 0,
+            total_compressed: 0,
+        }
+    }
+
+    /// Compress and stash a cold page, spilling to the companion file when the
+    /// blob would push the in-memory compressed cache over budget.
+    fn store(&mut self, page_idx: usize, page: &[u8]) -> std::io::Result<()> {
+        use std::io::{Seek, SeekFrom, Write};
+
+        let blob = zstd::encode_all(page, 3)?;
+        self.total_uncompressed += page.len() as u64;
+        self.total_compressed += blob.len() as u64;
+
+        // Drop any prior copy of this page so it never lives in both maps at
+        // once (which would let `take` return a stale blob) and so `mem_used`
+        // reflects only what is actually cached.
+        if let Some(old) = self.cache.remove(&page_idx) {
+            self.mem_used -= old.len();
+        }
+        self.spill.remove(&page_idx);
+
+        if self.mem_used + blob.len() <= self.mem_budget {
+            self.mem_used += blob.len();
+            self.cache.insert(page_idx, blob);
+        } else {
+            if self.spill_file.is_none() {
+                self.spill_file = Some(
+                    File::options()
+                        .read(true)
+                        .write(true)
+                        .create(true)
+                        .truncate(true)
+                        .open(&self.spill_path)?,
+                );
+            }
+            let file = self.spill_file.as_mut().unwrap();
+            let offset = file.seek(SeekFrom::End(0))?;
+            file.write_all(&blob)?;
+            self.spill.insert(page_idx, (offset, blob.len() as u32));
+        }
+        Ok(())
+    }
+
+    /// Remove and decompress a previously stored page, or `None` if this page
+    /// was never compressed (caller then falls back to plain mmap paging).
+    fn take(&mut self, page_idx: usize) -> std::io::Result<Option<Vec<u8>>> {
+        use std::io::{Read, Seek, SeekFrom};
+
+        if let Some(blob) = self.cache.remove(&page_idx) {
+            self.mem_used -= blob.len();
+            return Ok(Some(zstd::decode_all(&blob[..])?));
+        }
+        if let Some((offset, len)) = self.spill.remove(&page_idx) {
+            let file = self.spill_file.as_mut().ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::NotFound, "spill file missing")
+            })?;
+            file.seek(SeekFrom::Start(offset))?;
+            let mut blob = vec![0u8; len as usize];
+            file.read_exact(&mut blob)?;
+            return Ok(Some(zstd::decode_all(&blob[..])?));
+        }
+        Ok(None)
+    }
+
+    /// Achieved compression ratio (uncompressed / compressed), 1.0 before any
+    /// page has been compressed.
+    fn ratio(&self) -> f64 {
+        if self.total_compressed == 0 {
+            1.0
+        } else {
+            self.total_uncompressed as f64 / self.total_compressed as f64
+        }
+    }
 }
 
 impl QuantumMemoryManager {
@@ -49,9 +161,53 @@ impl QuantumMemoryManager {
             num_qubits,
             total_bytes,
             resident_bitmap: bitvec![0; total_pages],
+            dirty_bitmap: bitvec![0; total_pages],
+            compressor: None,
         })
     }
 
+    /// Enable transparent compression of cold pages, buffering up to
+    /// `mem_budget` bytes of compressed blobs in DRAM before spilling the rest
+    /// to a companion file at `spill_path`.
+    pub fn enable_compression(&mut self, spill_path: String, mem_budget: usize) {
+        self.compressor = Some(PageCompressor::new(spill_path, mem_budget));
+    }
+
+    /// Achieved cold-page compression ratio (uncompressed / compressed), or 1.0
+    /// when compression is disabled or nothing has been compressed yet.
+    pub fn compression_ratio(&self) -> f64 {
+        self.compressor.as_ref().map_or(1.0, PageCompressor::ratio)
+    }
+
+    /// Decompress any of the given pages that were previously compressed back
+    /// into the mmap region, so a gate reading them finds live data instead of
+    /// faulting from the raw backing store. Returns the number restored.
+    pub fn prefetch_decompress(&mut self, pages: &BitVec) -> usize {
+        if self.compressor.is_none() {
+            return 0;
+        }
+        let mut restored = 0;
+        let candidates: Vec<usize> = pages.iter_ones().collect();
+        for pg in candidates {
+            let decompressed = match self.compressor.as_mut().unwrap().take(pg) {
+                Ok(opt) => opt,
+                Err(e) => {
+                    println!("[Memory] Page {} decompression failed: {}", pg, e);
+                    None
+                }
+            };
+            if let Some(bytes) = decompressed {
+                let offset = pg * PAGE_SIZE;
+                let end = (offset + PAGE_SIZE).min(self.total_bytes);
+                let len = end - offset;
+                self.mapping[offset..end].copy_from_slice(&bytes[..len]);
+                self.resident_bitmap.set(pg, true);
+                restored += 1;
+            }
+        }
+        restored
+    }
+
     /// Unsafe access to the raw pointer for computation kernels
     /// The scheduler MUST ensure the relevant pages are resident before calling this.
     pub fn as_mut_slice(&mut self) -> &mut [u8] {
@@ -63,9 +219,167 @@ impl QuantumMemoryManager {
         self.mapping.as_ptr()
     }
 
+    /// Mark a set of pages as resident, e.g. after a prefetch submits them or
+    /// a gate faults them in. Keeps `resident_bitmap` an honest account of what
+    /// currently occupies DRAM so the budget enforcer can act on it.
+    pub fn mark_resident(&mut self, pages: &BitVec) {
+        for idx in pages.iter_ones() {
+            self.resident_bitmap.set(idx, true);
+        }
+    }
+
+    /// Number of pages currently believed resident in DRAM.
+    pub fn resident_page_count(&self) -> usize {
+        self.resident_bitmap.count_ones()
+    }
+
+    /// Evict resident pages until the resident set fits within `budget_pages`,
+    /// choosing victims by a Belady furthest-future-use policy: the page whose
+    /// next required gate index is largest — or which is never used again —
+    /// goes first. `next_use` maps a page index to the soonest future gate that
+    /// needs it; a page absent from the map is never used again and is the best
+    /// possible victim.
+    pub fn enforce_budget(&mut self, budget_pages: usize, next_use: &HashMap<usize, usize>) {
+        let resident_count = self.resident_bitmap.count_ones();
+        if resident_count <= budget_pages {
+            return;
+        }
+
+        let overflow = resident_count - budget_pages;
+        let mut resident: Vec<usize> = self.resident_bitmap.iter_ones().collect();
+        // Furthest next-use first; never-used pages (usize::MAX) sort to the front.
+        resident.sort_by_key(|pg| {
+            std::cmp::Reverse(next_use.get(pg).copied().unwrap_or(usize::MAX))
+        });
+
+        for &pg in resident.iter().take(overflow) {
+            self.evict_page(pg);
+        }
+    }
+
+    /// Content hash of the full state vector. This is O(state) and is only used
+    /// at checkpoint / restore boundaries (never per gate) to fingerprint the
+    /// *actual* base snapshot a journal is recorded against, so a journal is
+    /// never replayed onto an advanced or mismatched base.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.mapping[..].hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Record that a gate wrote the given pages. The next checkpoint journals
+    /// only these deltas instead of copying the whole backing file.
+    pub fn mark_dirty(&mut self, pages: &BitVec) {
+        for idx in pages.iter_ones() {
+            self.dirty_bitmap.set(idx, true);
+        }
+    }
+
+    /// Append the currently-dirty pages as one write-ahead journal segment and
+    /// clear the dirty set. A segment is a header
+    /// `[magic u32][base_hash u64][record_count u64]` followed by
+    /// `record_count` records of `(page_idx u64, [u8; PAGE_SIZE])`. This keeps
+    /// checkpointing O(pages touched) rather than O(whole state), and lets a
+    /// crash between gate batches be recovered by replaying the journal.
+    pub fn append_journal(&mut self, path: &str, base_hash: u64) -> std::io::Result<usize> {
+        use std::io::Write;
+
+        let dirty: Vec<usize> = self.dirty_bitmap.iter_ones().collect();
+        let mut file = File::options().create(true).append(true).open(path)?;
+
+        file.write_all(&JOURNAL_MAGIC.to_le_bytes())?;
+        file.write_all(&base_hash.to_le_bytes())?;
+        file.write_all(&(dirty.len() as u64).to_le_bytes())?;
+
+        let mut buf = [0u8; PAGE_SIZE];
+        for &pg in &dirty {
+            let offset = pg * PAGE_SIZE;
+            let end = (offset + PAGE_SIZE).min(self.total_bytes);
+            let len = end - offset;
+            buf[..len].copy_from_slice(&self.mapping[offset..end]);
+            if len < PAGE_SIZE {
+                // Zero-pad a trailing partial page so records stay fixed-size.
+                buf[len..].fill(0);
+            }
+            file.write_all(&(pg as u64).to_le_bytes())?;
+            file.write_all(&buf)?;
+        }
+        file.flush()?;
+
+        // Deltas are durable; reset for the next batch.
+        self.dirty_bitmap.fill(false);
+        Ok(dirty.len())
+    }
+
+    /// Replay a write-ahead journal back into the mmap, applying every segment's
+    /// dirty-page deltas in order. Expects the mmap to already hold the base
+    /// snapshot the journal was recorded against (`base_hash` guards against
+    /// replaying a journal onto the wrong base).
+    pub fn restore_journal(&mut self, path: &str, base_hash: u64) -> std::io::Result<usize> {
+        use std::io::Read;
+
+        let mut file = File::open(path)?;
+        let mut applied = 0;
+        let mut header = [0u8; 4 + 8 + 8];
+
+        loop {
+            match file.read_exact(&mut header) {
+                Ok(()) => {}
+                Err(ref e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            }
+
+            let magic = u32::from_le_bytes(header[0..4].try_into().unwrap());
+            if magic != JOURNAL_MAGIC {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "journal segment has bad magic",
+                ));
+            }
+            let seg_hash = u64::from_le_bytes(header[4..12].try_into().unwrap());
+            if seg_hash != base_hash {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "journal base hash does not match current state",
+                ));
+            }
+            let count = u64::from_le_bytes(header[12..20].try_into().unwrap());
+
+            let mut idx_buf = [0u8; 8];
+            let mut buf = [0u8; PAGE_SIZE];
+            for _ in 0..count {
+                file.read_exact(&mut idx_buf)?;
+                file.read_exact(&mut buf)?;
+                let pg = u64::from_le_bytes(idx_buf) as usize;
+                let offset = pg * PAGE_SIZE;
+                let end = (offset + PAGE_SIZE).min(self.total_bytes);
+                let len = end - offset;
+                self.mapping[offset..end].copy_from_slice(&buf[..len]);
+                applied += 1;
+            }
+        }
+
+        let _ = self.mapping.flush();
+        Ok(applied)
+    }
+
     /// Provide advice to OS to free pages (Eviction)
     pub fn evict_page(&mut self, page_idx: usize) {
         let offset = page_idx * PAGE_SIZE;
+
+        // Compress the cold page before discarding it, if enabled. Copy the
+        // bytes out first so the mmap read and the compressor borrow stay
+        // disjoint.
+        if self.compressor.is_some() {
+            let end = (offset + PAGE_SIZE).min(self.total_bytes);
+            let len = end - offset;
+            let mut page = [0u8; PAGE_SIZE];
+            page[..len].copy_from_slice(&self.mapping[offset..end]);
+            if let Err(e) = self.compressor.as_mut().unwrap().store(page_idx, &page) {
+                println!("[Memory] Page {} compression failed: {}", page_idx, e);
+            }
+        }
+
         unsafe {
             libc::madvise(
                 self.mapping.as_ptr().add(offset) as *mut _,